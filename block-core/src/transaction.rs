@@ -1,8 +1,11 @@
 use rlp::{UntrustedRlp, DecoderError, RlpStream, Encodable, Decodable};
 use bigint::{Address, U256, M256, H256};
 use sha3::{Digest, Keccak256};
+use secp256k1::{Secp256k1, Message, RecoverableSignature, RecoveryId};
+use core::cell::RefCell;
 
 #[cfg(not(feature = "std"))] use alloc::vec::Vec;
+#[cfg(not(feature = "std"))] use alloc::vec;
 #[cfg(not(feature = "std"))] use alloc::rc::Rc;
 
 // Use transaction action so we can keep most of the common fields
@@ -11,7 +14,8 @@ use sha3::{Digest, Keccak256};
 pub enum TransactionAction {
     Call(Address),
     Create,
-    /// CREATE2 transaction action with salt and code hash
+    /// CREATE2 transaction action with salt and code hash. Not a wire
+    /// format -- see the `Encodable`/`Decodable` impls below.
     Create2(H256, M256),
 }
 
@@ -39,23 +43,21 @@ impl TransactionAction {
     }
 }
 
-const CREATE2_TAG: u8 = 0xc2;
-
+// A transaction's `to` field is, on the real wire format, either empty
+// (contract creation) or a 20-byte address (call) -- there is no way to
+// tag CREATE2 at this level, since the choice between CREATE and CREATE2
+// is made by an opcode during execution, not carried in the transaction.
+// `Create2` therefore encodes identically to `Create`; `decode` never
+// produces it, and here it is only read, by `address()` and `intrinsic_gas()`.
 impl Encodable for TransactionAction {
     fn rlp_append(&self, s: &mut RlpStream) {
         match self {
             &TransactionAction::Call(address) => {
                 s.encoder().encode_value(&address);
             },
-            &TransactionAction::Create => {
+            &TransactionAction::Create | &TransactionAction::Create2(_, _) => {
                 s.encoder().encode_value(&[])
             },
-            &TransactionAction::Create2(salt, code_hash) => {
-                s.begin_list(3)
-                    .append(&CREATE2_TAG)
-                    .append(&salt)
-                    .append(&H256::from(code_hash));
-            }
         }
     }
 }
@@ -64,10 +66,6 @@ impl Decodable for TransactionAction {
     fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
         let action = if rlp.is_empty() {
             TransactionAction::Create
-        } else if let Ok(CREATE2_TAG) = rlp.val_at(0) {
-            let salt: H256 = rlp.val_at(1)?;
-            let code_hash: H256 = rlp.val_at(2)?;
-            TransactionAction::Create2(salt, M256::from(code_hash))
         } else {
             TransactionAction::Call(rlp.as_val()?)
         };
@@ -76,6 +74,543 @@ impl Decodable for TransactionAction {
     }
 }
 
+/// A fully signed transaction, as found in a mainnet/ETC block.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub nonce: U256,
+    pub gas_price: U256,
+    pub gas_limit: U256,
+    pub action: TransactionAction,
+    pub value: U256,
+    pub input: Vec<u8>,
+    /// EIP-155 encoded recovery id: `recovery_id + 35 + 2 * chain_id`,
+    /// or `recovery_id + 27` for pre-EIP-155 signatures.
+    pub v: u64,
+    pub r: H256,
+    pub s: H256,
+    /// Lazily-computed `keccak256(rlp(self))`, invalidated by `note_dirty`.
+    hash_cache: RefCell<Option<H256>>,
+}
+
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Transaction) -> bool {
+        self.nonce == other.nonce
+            && self.gas_price == other.gas_price
+            && self.gas_limit == other.gas_limit
+            && self.action == other.action
+            && self.value == other.value
+            && self.input == other.input
+            && self.v == other.v
+            && self.r == other.r
+            && self.s == other.s
+    }
+}
+
+impl Eq for Transaction {}
+
+impl Transaction {
+    /// The transaction's keccak256 hash, computed once and cached for
+    /// subsequent calls. Call `note_dirty` after mutating any field.
+    pub fn hash(&self) -> H256 {
+        if let Some(hash) = *self.hash_cache.borrow() {
+            return hash;
+        }
+
+        let hash = H256::from(Keccak256::digest(rlp::encode(self).as_slice()).as_slice());
+        *self.hash_cache.borrow_mut() = Some(hash);
+        hash
+    }
+
+    /// Invalidate the cached hash after mutating one of this
+    /// transaction's fields.
+    pub fn note_dirty(&mut self) {
+        *self.hash_cache.borrow_mut() = None;
+    }
+
+    /// Chain id encoded in `v`, if this signature follows EIP-155.
+    pub fn chain_id(&self) -> Option<u64> {
+        if self.v >= 35 {
+            Some((self.v - 35) / 2)
+        } else {
+            None
+        }
+    }
+
+    /// The 0/1 recovery id implied by `v`, rejecting any `v` outside
+    /// `{27, 28} ∪ [35, ∞)` instead of underflowing.
+    fn recovery_id(&self) -> Result<u8, secp256k1::Error> {
+        match self.chain_id() {
+            Some(chain_id) => self.v.checked_sub(35 + 2 * chain_id)
+                .map(|id| id as u8)
+                .ok_or(secp256k1::Error::InvalidRecoveryId),
+            None => match self.v {
+                27 => Ok(0),
+                28 => Ok(1),
+                _ => Err(secp256k1::Error::InvalidRecoveryId),
+            },
+        }
+    }
+
+    /// The RLP-encoded, keccak256-hashed payload that was signed to
+    /// produce `(v, r, s)`, per EIP-155 when a chain id is present.
+    pub fn signing_hash(&self, chain_id: Option<u64>) -> H256 {
+        let mut rlp = RlpStream::new_list(if chain_id.is_some() { 9 } else { 6 });
+        rlp.append(&self.nonce);
+        rlp.append(&self.gas_price);
+        rlp.append(&self.gas_limit);
+        rlp.append(&self.action);
+        rlp.append(&self.value);
+        rlp.append(&self.input);
+        if let Some(chain_id) = chain_id {
+            rlp.append(&chain_id);
+            rlp.append(&0u8);
+            rlp.append(&0u8);
+        }
+
+        H256::from(Keccak256::digest(rlp.out().as_slice()).as_slice())
+    }
+
+    /// Recover the sender address from the transaction's signature.
+    pub fn sender(&self) -> Result<Address, secp256k1::Error> {
+        let hash = self.signing_hash(self.chain_id());
+
+        let mut sig = [0u8; 64];
+        sig[0..32].copy_from_slice(&self.r);
+        sig[32..64].copy_from_slice(&self.s);
+
+        let context = Secp256k1::new();
+        let signature = RecoverableSignature::from_compact(
+            &context,
+            &sig,
+            RecoveryId::from_i32(self.recovery_id()? as i32)?,
+        )?;
+        let message = Message::from_slice(&hash)?;
+        let public_key = context.recover(&message, &signature)?;
+        let serialized = public_key.serialize_vec(&context, false);
+
+        // Drop the leading 0x04 tag byte before hashing the public key.
+        let hash = Keccak256::digest(&serialized[1..]);
+        Ok(Address::from(M256::from(&hash[12..])))
+    }
+}
+
+impl Encodable for Transaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(9);
+        s.append(&self.nonce);
+        s.append(&self.gas_price);
+        s.append(&self.gas_limit);
+        s.append(&self.action);
+        s.append(&self.value);
+        s.append(&self.input);
+        s.append(&self.v);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for Transaction {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        Ok(Transaction {
+            nonce: rlp.val_at(0)?,
+            gas_price: rlp.val_at(1)?,
+            gas_limit: rlp.val_at(2)?,
+            action: rlp.val_at(3)?,
+            value: rlp.val_at(4)?,
+            input: rlp.val_at(5)?,
+            v: rlp.val_at(6)?,
+            r: rlp.val_at(7)?,
+            s: rlp.val_at(8)?,
+            hash_cache: RefCell::new(None),
+        })
+    }
+}
+
+/// The envelope type byte of an EIP-2718 typed transaction.
+///
+/// Legacy transactions have no type byte; they are bare RLP lists and are
+/// distinguished from typed ones by the first byte being `>= 0x80`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxType {
+    Legacy,
+    AccessList = 0x01,
+    DynamicFee = 0x02,
+}
+
+impl TxType {
+    fn from_byte(byte: u8) -> Result<Self, DecoderError> {
+        match byte {
+            0x01 => Ok(TxType::AccessList),
+            0x02 => Ok(TxType::DynamicFee),
+            _ => Err(DecoderError::Custom("unknown transaction type")),
+        }
+    }
+}
+
+/// A transaction together with its EIP-2718 envelope type.
+///
+/// New payload variants are added here as they are supported; the legacy
+/// variant is always a bare RLP list with no leading type byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypedTransaction {
+    Legacy(Transaction),
+    AccessList(AccessListTransaction),
+    DynamicFee(DynamicFeeTransaction),
+}
+
+impl TypedTransaction {
+    pub fn tx_type(&self) -> TxType {
+        match self {
+            &TypedTransaction::Legacy(_) => TxType::Legacy,
+            &TypedTransaction::AccessList(_) => TxType::AccessList,
+            &TypedTransaction::DynamicFee(_) => TxType::DynamicFee,
+        }
+    }
+
+    /// Decode a transaction from its EIP-2718 wire representation: a type
+    /// byte followed by the RLP-encoded payload for non-legacy types, or
+    /// bare RLP for legacy ones.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecoderError> {
+        match bytes.first() {
+            Some(&byte) if byte < 0x80 => match TxType::from_byte(byte)? {
+                TxType::Legacy => unreachable!(),
+                TxType::AccessList => {
+                    let rlp = UntrustedRlp::new(&bytes[1..]);
+                    Ok(TypedTransaction::AccessList(AccessListTransaction::decode(&rlp)?))
+                },
+                TxType::DynamicFee => {
+                    let rlp = UntrustedRlp::new(&bytes[1..]);
+                    Ok(TypedTransaction::DynamicFee(DynamicFeeTransaction::decode(&rlp)?))
+                },
+            },
+            _ => {
+                let rlp = UntrustedRlp::new(bytes);
+                Ok(TypedTransaction::Legacy(Transaction::decode(&rlp)?))
+            },
+        }
+    }
+
+    /// Encode the transaction back to its EIP-2718 wire representation.
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            &TypedTransaction::Legacy(ref transaction) => rlp::encode(transaction).to_vec(),
+            &TypedTransaction::AccessList(ref transaction) => {
+                let mut encoded = vec![TxType::AccessList as u8];
+                encoded.extend_from_slice(&rlp::encode(transaction));
+                encoded
+            },
+            &TypedTransaction::DynamicFee(ref transaction) => {
+                let mut encoded = vec![TxType::DynamicFee as u8];
+                encoded.extend_from_slice(&rlp::encode(transaction));
+                encoded
+            },
+        }
+    }
+
+    /// The transaction's keccak256 hash over its full wire
+    /// representation (envelope type byte included for typed
+    /// transactions), cached per-variant and invalidated by
+    /// `note_dirty`.
+    pub fn hash(&self) -> H256 {
+        match self {
+            &TypedTransaction::Legacy(ref transaction) => transaction.hash(),
+            &TypedTransaction::AccessList(ref transaction) => transaction.hash(),
+            &TypedTransaction::DynamicFee(ref transaction) => transaction.hash(),
+        }
+    }
+
+    /// Invalidate the cached hash of the wrapped transaction after
+    /// mutating one of its fields.
+    pub fn note_dirty(&mut self) {
+        match self {
+            &mut TypedTransaction::Legacy(ref mut transaction) => transaction.note_dirty(),
+            &mut TypedTransaction::AccessList(ref mut transaction) => transaction.note_dirty(),
+            &mut TypedTransaction::DynamicFee(ref mut transaction) => transaction.note_dirty(),
+        }
+    }
+}
+
+/// A single EIP-2930 access list entry: an address and the storage keys
+/// within it that the transaction declares it will touch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessListItem(pub Address, pub Vec<H256>);
+
+impl Encodable for AccessListItem {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.0);
+        s.append_list(&self.1);
+    }
+}
+
+impl Decodable for AccessListItem {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        Ok(AccessListItem(rlp.val_at(0)?, rlp.list_at(1)?))
+    }
+}
+
+pub type AccessList = Vec<AccessListItem>;
+
+/// An EIP-2930 access-list transaction (envelope type `0x01`).
+#[derive(Clone, Debug)]
+pub struct AccessListTransaction {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub gas_price: U256,
+    pub gas_limit: U256,
+    pub action: TransactionAction,
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub access_list: AccessList,
+    pub v: u64,
+    pub r: H256,
+    pub s: H256,
+    /// Lazily-computed `keccak256(0x01 || rlp(self))`, invalidated by
+    /// `note_dirty`.
+    hash_cache: RefCell<Option<H256>>,
+}
+
+impl PartialEq for AccessListTransaction {
+    fn eq(&self, other: &AccessListTransaction) -> bool {
+        self.chain_id == other.chain_id
+            && self.nonce == other.nonce
+            && self.gas_price == other.gas_price
+            && self.gas_limit == other.gas_limit
+            && self.action == other.action
+            && self.value == other.value
+            && self.input == other.input
+            && self.access_list == other.access_list
+            && self.v == other.v
+            && self.r == other.r
+            && self.s == other.s
+    }
+}
+
+impl Eq for AccessListTransaction {}
+
+impl AccessListTransaction {
+    /// The transaction's keccak256 hash over its EIP-2718 envelope
+    /// (`0x01 || rlp(self)`), computed once and cached for subsequent
+    /// calls. Call `note_dirty` after mutating any field.
+    pub fn hash(&self) -> H256 {
+        if let Some(hash) = *self.hash_cache.borrow() {
+            return hash;
+        }
+
+        let mut bytes = Vec::new();
+        bytes.push(TxType::AccessList as u8);
+        bytes.extend_from_slice(&rlp::encode(self));
+        let hash = H256::from(Keccak256::digest(bytes.as_slice()).as_slice());
+        *self.hash_cache.borrow_mut() = Some(hash);
+        hash
+    }
+
+    /// Invalidate the cached hash after mutating one of this
+    /// transaction's fields.
+    pub fn note_dirty(&mut self) {
+        *self.hash_cache.borrow_mut() = None;
+    }
+
+    /// The gas charged before any execution takes place: the base cost,
+    /// the per-byte cost of the input data, the extra cost of contract
+    /// creation, and the EIP-2930 per-entry access list costs.
+    pub fn intrinsic_gas(&self) -> U256 {
+        let mut gas = U256::from(21000);
+
+        for byte in &self.input {
+            gas = gas + U256::from(if *byte == 0 { 4 } else { 16 });
+        }
+
+        match self.action {
+            TransactionAction::Create | TransactionAction::Create2(_, _) => {
+                gas = gas + U256::from(32000);
+            },
+            TransactionAction::Call(_) => {},
+        }
+
+        for item in &self.access_list {
+            gas = gas + U256::from(2400);
+            gas = gas + U256::from(1900) * U256::from(item.1.len());
+        }
+
+        gas
+    }
+}
+
+impl Encodable for AccessListTransaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(11);
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.gas_price);
+        s.append(&self.gas_limit);
+        s.append(&self.action);
+        s.append(&self.value);
+        s.append(&self.input);
+        s.append_list(&self.access_list);
+        s.append(&self.v);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for AccessListTransaction {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        Ok(AccessListTransaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            gas_price: rlp.val_at(2)?,
+            gas_limit: rlp.val_at(3)?,
+            action: rlp.val_at(4)?,
+            value: rlp.val_at(5)?,
+            input: rlp.val_at(6)?,
+            access_list: rlp.list_at(7)?,
+            v: rlp.val_at(8)?,
+            r: rlp.val_at(9)?,
+            s: rlp.val_at(10)?,
+            hash_cache: RefCell::new(None),
+        })
+    }
+}
+
+/// An error returned when an EIP-1559 fee cap is invalid for a given
+/// base fee.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeValidationError {
+    /// `max_fee_per_gas` is lower than the block's base fee.
+    MaxFeeBelowBaseFee,
+    /// `max_fee_per_gas` is lower than `max_priority_fee_per_gas`.
+    MaxFeeBelowPriorityFee,
+}
+
+/// An EIP-1559 dynamic-fee transaction (envelope type `0x02`).
+#[derive(Clone, Debug)]
+pub struct DynamicFeeTransaction {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: U256,
+    pub action: TransactionAction,
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub access_list: AccessList,
+    pub v: u64,
+    pub r: H256,
+    pub s: H256,
+    /// Lazily-computed `keccak256(0x02 || rlp(self))`, invalidated by
+    /// `note_dirty`.
+    hash_cache: RefCell<Option<H256>>,
+}
+
+impl PartialEq for DynamicFeeTransaction {
+    fn eq(&self, other: &DynamicFeeTransaction) -> bool {
+        self.chain_id == other.chain_id
+            && self.nonce == other.nonce
+            && self.max_priority_fee_per_gas == other.max_priority_fee_per_gas
+            && self.max_fee_per_gas == other.max_fee_per_gas
+            && self.gas_limit == other.gas_limit
+            && self.action == other.action
+            && self.value == other.value
+            && self.input == other.input
+            && self.access_list == other.access_list
+            && self.v == other.v
+            && self.r == other.r
+            && self.s == other.s
+    }
+}
+
+impl Eq for DynamicFeeTransaction {}
+
+impl DynamicFeeTransaction {
+    /// The transaction's keccak256 hash over its EIP-2718 envelope
+    /// (`0x02 || rlp(self)`), computed once and cached for subsequent
+    /// calls. Call `note_dirty` after mutating any field.
+    pub fn hash(&self) -> H256 {
+        if let Some(hash) = *self.hash_cache.borrow() {
+            return hash;
+        }
+
+        let mut bytes = Vec::new();
+        bytes.push(TxType::DynamicFee as u8);
+        bytes.extend_from_slice(&rlp::encode(self));
+        let hash = H256::from(Keccak256::digest(bytes.as_slice()).as_slice());
+        *self.hash_cache.borrow_mut() = Some(hash);
+        hash
+    }
+
+    /// Invalidate the cached hash after mutating one of this
+    /// transaction's fields.
+    pub fn note_dirty(&mut self) {
+        *self.hash_cache.borrow_mut() = None;
+    }
+
+    /// Check that the fee caps are consistent with each other and with
+    /// the given block base fee.
+    pub fn validate(&self, base_fee: U256) -> Result<(), FeeValidationError> {
+        if self.max_fee_per_gas < base_fee {
+            return Err(FeeValidationError::MaxFeeBelowBaseFee);
+        }
+        if self.max_fee_per_gas < self.max_priority_fee_per_gas {
+            return Err(FeeValidationError::MaxFeeBelowPriorityFee);
+        }
+        Ok(())
+    }
+
+    /// The gas price actually paid per unit of gas: the smaller of
+    /// `max_fee_per_gas` and `base_fee + max_priority_fee_per_gas`, never
+    /// below `base_fee` even for a transaction that fails `validate`.
+    pub fn effective_gas_price(&self, base_fee: U256) -> U256 {
+        let capped = core::cmp::min(self.max_fee_per_gas, base_fee + self.max_priority_fee_per_gas);
+        core::cmp::max(capped, base_fee)
+    }
+
+    /// The portion of `effective_gas_price` that goes to the block
+    /// producer rather than being burned as base fee. Zero when
+    /// `max_fee_per_gas` is below `base_fee`.
+    pub fn effective_priority_fee(&self, base_fee: U256) -> U256 {
+        self.effective_gas_price(base_fee) - base_fee
+    }
+}
+
+impl Encodable for DynamicFeeTransaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(12);
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.max_priority_fee_per_gas);
+        s.append(&self.max_fee_per_gas);
+        s.append(&self.gas_limit);
+        s.append(&self.action);
+        s.append(&self.value);
+        s.append(&self.input);
+        s.append_list(&self.access_list);
+        s.append(&self.v);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for DynamicFeeTransaction {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        Ok(DynamicFeeTransaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            max_priority_fee_per_gas: rlp.val_at(2)?,
+            max_fee_per_gas: rlp.val_at(3)?,
+            gas_limit: rlp.val_at(4)?,
+            action: rlp.val_at(5)?,
+            value: rlp.val_at(6)?,
+            input: rlp.val_at(7)?,
+            access_list: rlp.list_at(8)?,
+            v: rlp.val_at(9)?,
+            r: rlp.val_at(10)?,
+            s: rlp.val_at(11)?,
+            hash_cache: RefCell::new(None),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,12 +634,287 @@ mod tests {
     }
 
     #[test]
-    fn rlp_roundtrip_create2() {
+    fn create2_encodes_like_create_and_decodes_as_create() {
+        // CREATE2 is an execution-time opcode, not a transaction-level
+        // concept, so it is indistinguishable from CREATE on the wire.
         let salt = H256::from(M256::from(std::i32::MAX));
         let code_hash = M256::from(1024);
         let action = TransactionAction::Create2(salt, code_hash);
         let encoded = rlp::encode(&action);
+        assert_eq!(encoded, rlp::encode(&TransactionAction::Create));
+
         let decoded: TransactionAction = rlp::decode(&encoded);
-        assert_eq!(action, decoded);
+        assert_eq!(decoded, TransactionAction::Create);
+    }
+
+    #[test]
+    fn rlp_roundtrip_transaction() {
+        let transaction = Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Call(Address::from(M256::from(1))),
+            value: U256::from(100),
+            input: Vec::new(),
+            v: 27,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+        let encoded = rlp::encode(&transaction);
+        let decoded: Transaction = rlp::decode(&encoded);
+        assert_eq!(transaction, decoded);
+    }
+
+    #[test]
+    fn chain_id_decoded_from_eip155_v() {
+        let mut transaction = Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            v: 37,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+        assert_eq!(transaction.chain_id(), Some(1));
+
+        transaction.v = 27;
+        assert_eq!(transaction.chain_id(), None);
+    }
+
+    #[test]
+    fn decodes_legacy_envelope() {
+        let transaction = Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            v: 27,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+        let typed = TypedTransaction::Legacy(transaction.clone());
+        let encoded = typed.encode();
+
+        let decoded = TypedTransaction::decode(&encoded).unwrap();
+        assert_eq!(decoded.tx_type(), TxType::Legacy);
+        assert_eq!(decoded, TypedTransaction::Legacy(transaction));
+    }
+
+    #[test]
+    fn decodes_access_list_envelope() {
+        let transaction = AccessListTransaction {
+            chain_id: 1,
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Call(Address::from(M256::from(1))),
+            value: U256::zero(),
+            input: Vec::new(),
+            access_list: vec![AccessListItem(Address::from(M256::from(2)), vec![H256::from(M256::from(3))])],
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+        let typed = TypedTransaction::AccessList(transaction.clone());
+        let encoded = typed.encode();
+
+        let decoded = TypedTransaction::decode(&encoded).unwrap();
+        assert_eq!(decoded.tx_type(), TxType::AccessList);
+        assert_eq!(decoded, TypedTransaction::AccessList(transaction));
+    }
+
+    #[test]
+    fn intrinsic_gas_accounts_for_access_list_and_creation() {
+        let transaction = AccessListTransaction {
+            chain_id: 1,
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: vec![0x00, 0x01],
+            access_list: vec![AccessListItem(
+                Address::from(M256::from(2)),
+                vec![H256::from(M256::from(3)), H256::from(M256::from(4))],
+            )],
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+
+        // 21000 base + 32000 create + 4 (zero byte) + 16 (non-zero byte)
+        // + 2400 (one address) + 2 * 1900 (two storage keys)
+        assert_eq!(transaction.intrinsic_gas(), U256::from(21000 + 32000 + 4 + 16 + 2400 + 2 * 1900));
+    }
+
+    #[test]
+    fn decodes_dynamic_fee_envelope() {
+        let transaction = DynamicFeeTransaction {
+            chain_id: 1,
+            nonce: U256::zero(),
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(10),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Call(Address::from(M256::from(1))),
+            value: U256::zero(),
+            input: Vec::new(),
+            access_list: Vec::new(),
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+        let typed = TypedTransaction::DynamicFee(transaction.clone());
+        let encoded = typed.encode();
+
+        let decoded = TypedTransaction::decode(&encoded).unwrap();
+        assert_eq!(decoded.tx_type(), TxType::DynamicFee);
+        assert_eq!(decoded, TypedTransaction::DynamicFee(transaction));
+    }
+
+    #[test]
+    fn effective_gas_price_is_capped_by_max_fee() {
+        let transaction = DynamicFeeTransaction {
+            chain_id: 1,
+            nonce: U256::zero(),
+            max_priority_fee_per_gas: U256::from(5),
+            max_fee_per_gas: U256::from(10),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            access_list: Vec::new(),
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+
+        assert!(transaction.validate(U256::from(8)).is_ok());
+        assert_eq!(transaction.effective_gas_price(U256::from(8)), U256::from(10));
+        assert_eq!(transaction.effective_priority_fee(U256::from(8)), U256::from(2));
+
+        assert_eq!(
+            transaction.validate(U256::from(11)),
+            Err(FeeValidationError::MaxFeeBelowBaseFee),
+        );
+    }
+
+    #[test]
+    fn effective_fee_does_not_underflow_when_max_fee_below_base_fee() {
+        let transaction = DynamicFeeTransaction {
+            chain_id: 1,
+            nonce: U256::zero(),
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(5),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            access_list: Vec::new(),
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+
+        assert!(transaction.validate(U256::from(10)).is_err());
+        assert_eq!(transaction.effective_gas_price(U256::from(10)), U256::from(10));
+        assert_eq!(transaction.effective_priority_fee(U256::from(10)), U256::zero());
+    }
+
+    #[test]
+    fn hash_is_cached_until_marked_dirty() {
+        let mut transaction = Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            v: 27,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+
+        let first = transaction.hash();
+        assert_eq!(transaction.hash(), first);
+
+        transaction.nonce = U256::from(1);
+        transaction.note_dirty();
+        assert_ne!(transaction.hash(), first);
+    }
+
+    #[test]
+    fn sender_rejects_v_out_of_range() {
+        let transaction = Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        };
+
+        assert!(transaction.sender().is_err());
+    }
+
+    #[test]
+    fn typed_transaction_hash_is_cached_and_distinct_per_envelope_type() {
+        let mut access_list = TypedTransaction::AccessList(AccessListTransaction {
+            chain_id: 1,
+            nonce: U256::zero(),
+            gas_price: U256::from(1),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            access_list: Vec::new(),
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        });
+
+        let first = access_list.hash();
+        assert_eq!(access_list.hash(), first);
+
+        if let TypedTransaction::AccessList(ref mut transaction) = access_list {
+            transaction.nonce = U256::from(1);
+        }
+        access_list.note_dirty();
+        assert_ne!(access_list.hash(), first);
+
+        let dynamic_fee = TypedTransaction::DynamicFee(DynamicFeeTransaction {
+            chain_id: 1,
+            nonce: U256::from(1),
+            max_priority_fee_per_gas: U256::zero(),
+            max_fee_per_gas: U256::zero(),
+            gas_limit: U256::from(21000),
+            action: TransactionAction::Create,
+            value: U256::zero(),
+            input: Vec::new(),
+            access_list: Vec::new(),
+            v: 0,
+            r: H256::from(M256::from(1)),
+            s: H256::from(M256::from(2)),
+            hash_cache: RefCell::new(None),
+        });
+        assert_ne!(access_list.hash(), dynamic_fee.hash());
     }
 }